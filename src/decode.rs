@@ -0,0 +1,245 @@
+//! DTMF decoding via the Goertzel algorithm.
+//!
+//! This is the inverse of `to_frequencies`: instead of turning a keypress
+//! into a pair of tones, it looks at a buffer of samples and recovers the
+//! keypresses that produced it. A full FFT would be overkill since we only
+//! ever care about eight fixed frequencies, so each block is scored against
+//! those eight bins directly with the single-bin Goertzel recurrence.
+
+use std::collections::VecDeque;
+
+use crate::{Output, CHANNELS};
+
+/// Low-group DTMF frequencies, in Hz.
+const LOW_FREQS: [f64; 4] = [697.0, 770.0, 852.0, 941.0];
+/// High-group DTMF frequencies, in Hz.
+const HIGH_FREQS: [f64; 4] = [1209.0, 1336.0, 1477.0, 1633.0];
+
+/// Default Goertzel block length, in milliseconds. A little over 25 ms is
+/// long enough to resolve the eight DTMF bins but short enough that a
+/// single keypress (~70 ms) spans several blocks. The actual block size in
+/// samples is derived from this and whatever `sample_hz` the decoder is
+/// constructed with, since a sample *count* tuned for one rate (the 205
+/// samples this works out to at 8 kHz) aliases nearby DTMF frequencies onto
+/// the same bin at other rates.
+pub const DEFAULT_BLOCK_MS: f64 = 25.625;
+
+/// Blocks overlap by `1 - 1/HOP_DIVISOR`, so a keypress that starts midway
+/// through one analysis window still gets analysed in full on the next hop
+/// instead of waiting a whole block length.
+const HOP_DIVISOR: usize = 4;
+
+/// Minimum bin power (in the same units as `power`, i.e. squared amplitude
+/// summed over the block) for a tone to be considered present at all.
+const ENERGY_THRESHOLD: f64 = 4.0;
+
+/// Maximum allowed ratio, in dB, between the low-group and high-group bin
+/// powers. Real DTMF allows the high group to run a little hotter than the
+/// low group ("twist"), but a ratio outside this range is more likely noise
+/// or speech than a real keypress.
+const TWIST_TOLERANCE_DB: f64 = 8.0;
+
+/// An in-group bin within this many dB of the winning bin means we can't
+/// tell which tone is really playing, so the block is rejected.
+const GUARD_DB: f64 = 5.0;
+
+fn to_db(power: f64) -> f64 {
+    10.0 * power.max(1e-12).log10()
+}
+
+/// Reverse of `to_frequencies`: map a (high, low) frequency pair back to the
+/// character that would have produced it.
+fn from_frequencies(high: Frequency, low: Frequency) -> Option<char> {
+    let high_index = HIGH_FREQS.iter().position(|&f| (f - high).abs() < 1.0)?;
+    let low_index = LOW_FREQS.iter().position(|&f| (f - low).abs() < 1.0)?;
+    Some(
+        [
+            ['1', '2', '3', 'A'],
+            ['4', '5', '6', 'B'],
+            ['7', '8', '9', 'C'],
+            ['*', '0', '#', 'D'],
+        ][low_index][high_index],
+    )
+}
+
+type Frequency = f64;
+
+/// A single precomputed Goertzel bin.
+#[derive(Debug, Clone, Copy)]
+struct Bin {
+    frequency: Frequency,
+    coeff: f64,
+}
+
+impl Bin {
+    fn new(frequency: Frequency, sample_hz: f64, block_size: usize) -> Bin {
+        let k = (block_size as f64 * frequency / sample_hz).round();
+        let omega = 2.0 * std::f64::consts::PI * k / block_size as f64;
+        Bin {
+            frequency,
+            coeff: 2.0 * omega.cos(),
+        }
+    }
+
+    /// Run the Goertzel recurrence over `block` and return the bin power.
+    fn power(&self, block: &[Output]) -> f64 {
+        let mut s1 = 0.0;
+        let mut s2 = 0.0;
+        for &sample in block {
+            let s = sample as f64 + self.coeff * s1 - s2;
+            s2 = s1;
+            s1 = s;
+        }
+        s1 * s1 + s2 * s2 - self.coeff * s1 * s2
+    }
+}
+
+/// Find the strongest bin in a group, along with how many dB clear of
+/// runner-up it is.
+fn strongest(bins: &[Bin], powers: &[f64]) -> (usize, f64) {
+    let winner = (0..bins.len())
+        .max_by(|&a, &b| powers[a].partial_cmp(&powers[b]).unwrap())
+        .unwrap();
+
+    let runner_up = (0..bins.len())
+        .filter(|&i| i != winner)
+        .map(|i| to_db(powers[winner]) - to_db(powers[i]))
+        .fold(f64::INFINITY, f64::min);
+
+    (winner, runner_up)
+}
+
+/// Decodes DTMF tones out of a stream of audio using a sliding, overlapping
+/// Goertzel window.
+///
+/// Samples are fed in via `push` (or `push_frames` for multichannel audio)
+/// and decoded characters come back as they're found. Repeated detections
+/// of the same digit across consecutive, overlapping windows are debounced
+/// so that a single keypress yields a single character.
+pub struct Decoder {
+    sample_hz: f64,
+    block_size: usize,
+    hop_size: usize,
+    low_bins: [Bin; 4],
+    high_bins: [Bin; 4],
+    window: VecDeque<Output>,
+    samples_since_analysis: usize,
+    last: Option<char>,
+}
+
+impl Decoder {
+    /// A decoder using `DEFAULT_BLOCK_MS`, sized to `sample_hz` so the
+    /// Goertzel bins stay distinct whatever rate it's decoding at.
+    pub fn new(sample_hz: f64) -> Decoder {
+        let block_size = ((DEFAULT_BLOCK_MS / 1_000.0 * sample_hz).round() as usize).max(1);
+        Decoder::with_block_size(sample_hz, block_size)
+    }
+
+    /// A decoder with an explicit block size, in samples, analysed every
+    /// `block_size / HOP_DIVISOR` samples. `block_size` is clamped to at
+    /// least 1, as `Sequencer::with_timing` clamps its sample counts, since
+    /// a block size of 0 would make each bin's `omega` a `0.0 / 0.0`
+    /// division and turn every bin power into `NaN`.
+    pub fn with_block_size(sample_hz: f64, block_size: usize) -> Decoder {
+        let block_size = block_size.max(1);
+        let bin = |f: f64| Bin::new(f, sample_hz, block_size);
+        Decoder {
+            sample_hz,
+            block_size,
+            hop_size: (block_size / HOP_DIVISOR).max(1),
+            low_bins: LOW_FREQS.map(bin),
+            high_bins: HIGH_FREQS.map(bin),
+            window: VecDeque::with_capacity(block_size),
+            samples_since_analysis: 0,
+            last: None,
+        }
+    }
+
+    /// Feed a single mono sample in, returning a character if a new keypress
+    /// was just confirmed.
+    pub fn push(&mut self, sample: Output) -> Option<char> {
+        self.window.push_back(sample);
+        if self.window.len() > self.block_size {
+            self.window.pop_front();
+        }
+        self.samples_since_analysis += 1;
+
+        if self.window.len() < self.block_size || self.samples_since_analysis < self.hop_size {
+            return None;
+        }
+        self.samples_since_analysis = 0;
+
+        let detected = self.analyse_block();
+
+        match detected {
+            Some(c) if self.last != Some(c) => {
+                self.last = Some(c);
+                Some(c)
+            }
+            Some(_) => None,
+            None => {
+                self.last = None;
+                None
+            }
+        }
+    }
+
+    /// Feed multichannel frames in, averaging channels down to mono first.
+    pub fn push_frames(&mut self, frames: &[[Output; CHANNELS]]) -> Vec<char> {
+        let mut out = Vec::new();
+        for frame in frames {
+            let mono = frame.iter().sum::<Output>() / CHANNELS as Output;
+            if let Some(c) = self.push(mono) {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    fn analyse_block(&mut self) -> Option<char> {
+        let block = self.window.make_contiguous();
+
+        let low_powers: Vec<f64> = self.low_bins.iter().map(|b| b.power(block)).collect();
+        let high_powers: Vec<f64> = self.high_bins.iter().map(|b| b.power(block)).collect();
+
+        let (low_index, low_guard) = strongest(&self.low_bins, &low_powers);
+        let (high_index, high_guard) = strongest(&self.high_bins, &high_powers);
+
+        let low_power = low_powers[low_index];
+        let high_power = high_powers[high_index];
+
+        if low_power < ENERGY_THRESHOLD || high_power < ENERGY_THRESHOLD {
+            return None;
+        }
+        if low_guard < GUARD_DB || high_guard < GUARD_DB {
+            return None;
+        }
+        if (to_db(high_power) - to_db(low_power)).abs() > TWIST_TOLERANCE_DB {
+            return None;
+        }
+
+        from_frequencies(self.high_bins[high_index].frequency, self.low_bins[low_index].frequency)
+    }
+
+    pub fn sample_hz(&self) -> f64 {
+        self.sample_hz
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::render_string;
+    use crate::{DEFAULT_GAP_MS, DEFAULT_TONE_MS, DEFAULT_TWIST_DB, SAMPLE_HZ};
+
+    #[test]
+    fn roundtrips_rendered_digits() {
+        let digits = "1470*26A";
+        let buffer = render_string(digits, DEFAULT_TONE_MS, DEFAULT_GAP_MS, DEFAULT_TWIST_DB, SAMPLE_HZ);
+
+        let mut decoder = Decoder::new(SAMPLE_HZ);
+        let decoded: String = decoder.push_frames(&buffer).into_iter().collect();
+
+        assert_eq!(decoded, digits);
+    }
+}
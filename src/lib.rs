@@ -0,0 +1,257 @@
+//! Core DSP graph types shared between the realtime PortAudio binary and
+//! the offline rendering/decoding utilities.
+//!
+//! This crate builds a small `dsp::Graph` of sine oscillators feeding a
+//! master `Synth` node to produce DTMF tones. `main.rs` drives that graph
+//! in realtime from a PortAudio callback; `render` drives the same graph
+//! offline into an owned buffer.
+
+extern crate dsp;
+extern crate rand;
+
+use dsp::{Frame, FromSample, Node, Sample};
+
+pub mod decode;
+pub mod render;
+pub mod sequencer;
+pub mod wav;
+
+pub use sequencer::{Sequencer, SequencerEvent};
+
+/// SoundStream is currently generic over i8, i32 and f32. Feel free to change
+/// it!
+pub type Output = f32;
+
+pub type Phase = f64;
+pub type Frequency = f64;
+pub type Volume = f32;
+
+pub const CHANNELS: usize = 2;
+pub const SAMPLE_HZ: f64 = 44_100.0;
+
+/// Default attack/release length, chosen to be short enough not to blur
+/// DTMF timing but long enough to get rid of the click you get from an
+/// instantaneous volume change mid-waveform.
+const DEFAULT_ENVELOPE_MS: f64 = 5.0;
+
+/// Default tone-on duration: the 70 ms on / 70 ms off region that real DTMF
+/// receivers accept.
+pub const DEFAULT_TONE_MS: f64 = 70.0;
+/// Default inter-digit gap duration.
+pub const DEFAULT_GAP_MS: f64 = 70.0;
+/// Default twist: how much louder (in dB) the high-group tone is played
+/// relative to the low-group tone, as telephony specs require.
+pub const DEFAULT_TWIST_DB: f64 = 2.0;
+
+/// A command sent from the caller to the realtime audio callback.
+pub enum Command {
+    /// Enqueue a character to be dialled once any tones ahead of it in the
+    /// sequencer have finished.
+    Enqueue(char),
+}
+
+/// Which stage of the ADSR envelope an oscillator is currently in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeStage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// A per-oscillator ADSR envelope, advanced one sample at a time and
+/// multiplied into the raw waveform so that turning a tone on or off ramps
+/// smoothly instead of snapping straight to the target volume.
+#[derive(Debug, Clone, Copy)]
+pub struct Envelope {
+    attack: usize,
+    decay: usize,
+    sustain: Volume,
+    release: usize,
+    gate: bool,
+    stage: EnvelopeStage,
+    samples_in_stage: usize,
+    level: Volume,
+    release_from: Volume,
+}
+
+impl Envelope {
+    /// An envelope with a short attack/release and no decay, suitable for
+    /// DTMF tone bursts.
+    pub fn new(sample_hz: f64) -> Envelope {
+        let ramp_samples = (DEFAULT_ENVELOPE_MS / 1_000.0 * sample_hz).round() as usize;
+        Envelope {
+            attack: ramp_samples.max(1),
+            decay: 1,
+            sustain: 1.0,
+            release: ramp_samples.max(1),
+            gate: false,
+            stage: EnvelopeStage::Idle,
+            samples_in_stage: 0,
+            level: 0.0,
+            release_from: 0.0,
+        }
+    }
+
+    /// Open the gate, restarting the envelope from the attack stage.
+    pub fn gate_on(&mut self) {
+        self.gate = true;
+        self.stage = EnvelopeStage::Attack;
+        self.samples_in_stage = 0;
+    }
+
+    /// Close the gate, starting the release ramp from whatever level the
+    /// envelope is currently at.
+    pub fn gate_off(&mut self) {
+        self.gate = false;
+        self.release_from = self.level;
+        self.stage = EnvelopeStage::Release;
+        self.samples_in_stage = 0;
+    }
+
+    /// Advance the envelope by one sample and return the new level.
+    pub fn step(&mut self) -> Volume {
+        match self.stage {
+            EnvelopeStage::Idle => self.level = 0.0,
+
+            EnvelopeStage::Attack => {
+                self.samples_in_stage += 1;
+                self.level = self.samples_in_stage as Volume / self.attack as Volume;
+                if self.samples_in_stage >= self.attack {
+                    self.stage = EnvelopeStage::Decay;
+                    self.samples_in_stage = 0;
+                }
+            }
+
+            EnvelopeStage::Decay => {
+                self.samples_in_stage += 1;
+                let t = self.samples_in_stage as Volume / self.decay as Volume;
+                self.level = 1.0 - t * (1.0 - self.sustain);
+                if self.samples_in_stage >= self.decay {
+                    self.stage = EnvelopeStage::Sustain;
+                    self.samples_in_stage = 0;
+                    self.level = self.sustain;
+                }
+            }
+
+            EnvelopeStage::Sustain => self.level = self.sustain,
+
+            EnvelopeStage::Release => {
+                self.samples_in_stage += 1;
+                let t = self.samples_in_stage as Volume / self.release as Volume;
+                self.level = self.release_from * (1.0 - t).max(0.0);
+                if self.samples_in_stage >= self.release {
+                    self.stage = EnvelopeStage::Idle;
+                    self.level = 0.0;
+                }
+            }
+        }
+
+        self.level
+    }
+}
+
+pub fn to_frequencies(character: char) -> Option<(Frequency, Frequency)> {
+    match character {
+        '1' => Some((1209.0, 697.0)),
+        '2' => Some((1336.0, 697.0)),
+        '3' => Some((1477.0, 697.0)),
+        'A' => Some((1633.0, 697.0)),
+        '4' => Some((1209.0, 770.0)),
+        '5' => Some((1336.0, 770.0)),
+        '6' => Some((1477.0, 770.0)),
+        'B' => Some((1633.0, 770.0)),
+        '7' => Some((1209.0, 852.0)),
+        '8' => Some((1336.0, 852.0)),
+        '9' => Some((1477.0, 852.0)),
+        'C' => Some((1633.0, 852.0)),
+        '*' => Some((1209.0, 941.0)),
+        '0' => Some((1336.0, 941.0)),
+        '#' => Some((1477.0, 941.0)),
+        'D' => Some((1633.0, 941.0)),
+        _ => None,
+    }
+}
+
+/// How the master `Synth` node lays its input out across the output
+/// channels. Borrowed from HexoDSP's `Out` node, which has the same
+/// `mono`/`vol` pair on its master output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Average every channel together and copy the result to all of them, for
+    /// single-channel telephony output over a stereo stream.
+    Mono,
+    /// Leave each channel as the graph mixed it.
+    Stereo,
+}
+
+/// Our type for which we will implement the `Dsp` trait.
+#[derive(Debug)]
+pub enum DspNode {
+    /// Synth will be our demonstration of a master GraphNode. Holds the
+    /// output mode and master volume applied to the graph's mixed signal.
+    Synth(OutputMode, Volume),
+    /// Oscillator will be our generator type of node, meaning that we will override
+    /// the way it provides audio via its `audio_requested` method. The envelope
+    /// is stepped once per sample and multiplied into the waveform so that
+    /// gating the oscillator on or off ramps smoothly instead of clicking.
+    Oscillator(Phase, Frequency, Volume, Envelope),
+}
+
+impl Node<[Output; CHANNELS]> for DspNode {
+    /// Here we'll override the audio_requested method and generate a sine wave.
+    fn audio_requested(&mut self, buffer: &mut [[Output; CHANNELS]], sample_hz: f64) {
+        match *self {
+            DspNode::Synth(mode, master_volume) => {
+                dsp::slice::map_in_place(buffer, |frame| match mode {
+                    OutputMode::Mono => {
+                        let mixed = frame.iter().sum::<Output>() / CHANNELS as Output * master_volume;
+                        Frame::from_fn(|_| mixed)
+                    }
+                    OutputMode::Stereo => Frame::from_fn(|i| frame[i] * master_volume),
+                });
+            }
+            DspNode::Oscillator(ref mut phase, frequency, volume, ref mut envelope) => {
+                dsp::slice::map_in_place(buffer, |_| {
+                    let val = sine_wave(*phase, volume * envelope.step());
+                    *phase += frequency / sample_hz;
+                    Frame::from_fn(|_| val)
+                });
+            },
+        }
+    }
+}
+
+/// Return a sine wave for the given phase.
+pub fn sine_wave<S: Sample>(phase: Phase, volume: Volume) -> S
+    where S: Sample + FromSample<f32>,
+{
+    use std::f64::consts::PI;
+    ((phase * PI * 2.0).sin() as f32 * volume).to_sample::<S>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mono_synth_averages_channels_instead_of_summing() {
+        let mut synth = DspNode::Synth(OutputMode::Mono, 1.0);
+        let mut buffer = [[2.0, 4.0]];
+
+        synth.audio_requested(&mut buffer, SAMPLE_HZ);
+
+        assert_eq!(buffer, [[3.0, 3.0]]);
+    }
+
+    #[test]
+    fn mono_synth_applies_master_volume_after_averaging() {
+        let mut synth = DspNode::Synth(OutputMode::Mono, 0.5);
+        let mut buffer = [[2.0, 4.0]];
+
+        synth.audio_requested(&mut buffer, SAMPLE_HZ);
+
+        assert_eq!(buffer, [[1.5, 1.5]]);
+    }
+}
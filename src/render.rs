@@ -0,0 +1,75 @@
+//! Offline rendering of DTMF dial strings.
+//!
+//! Drives the same `Graph`/`DspNode` setup as the realtime PortAudio
+//! example, but writes into an owned buffer instead of a live audio
+//! stream. Useful for generating precise, reproducible DTMF files for
+//! testing telephony equipment or for feeding [`crate::decode`], without
+//! depending on stdin timing or `thread::sleep`.
+
+use dsp::{Graph, Node};
+
+use crate::{to_frequencies, DspNode, Envelope, Output, OutputMode, CHANNELS};
+
+/// Render `digits` to a buffer of interleaved sample frames.
+///
+/// `tone_ms` is how long each tone is held for and `gap_ms` is the silence
+/// left between digits; characters with no DTMF mapping are skipped.
+/// `twist_db` is how much louder (in dB) the high-group tone is played
+/// relative to the low-group tone, matching the realtime sequencer's twist
+/// so files rendered here are representative of what real telephony
+/// equipment under test will receive.
+pub fn render_string(
+    digits: &str,
+    tone_ms: f64,
+    gap_ms: f64,
+    twist_db: f64,
+    sample_hz: f64,
+) -> Vec<[Output; CHANNELS]> {
+    let twist = 10f64.powf(twist_db / 20.0) as crate::Volume;
+
+    let mut graph = Graph::new();
+
+    let synth = graph.add_node(DspNode::Synth(OutputMode::Stereo, 1.0));
+    let (_, oscillator_a) =
+        graph.add_input(DspNode::Oscillator(0.0, 0.0, 0.2 * twist, Envelope::new(sample_hz)), synth);
+    let (_, oscillator_b) = graph.add_input(DspNode::Oscillator(0.0, 0.0, 0.2, Envelope::new(sample_hz)), synth);
+    graph.set_master(Some(synth));
+
+    let tone_frames = (tone_ms / 1_000.0 * sample_hz).round() as usize;
+    let gap_frames = (gap_ms / 1_000.0 * sample_hz).round() as usize;
+
+    let mut buffer = Vec::new();
+
+    for character in digits.chars() {
+        let (freq_a, freq_b) = match to_frequencies(character) {
+            Some(frequencies) => frequencies,
+            None => continue,
+        };
+
+        if let DspNode::Oscillator(_, ref mut pitch, _, ref mut envelope) = graph[oscillator_a] {
+            *pitch = freq_a;
+            envelope.gate_on();
+        }
+        if let DspNode::Oscillator(_, ref mut pitch, _, ref mut envelope) = graph[oscillator_b] {
+            *pitch = freq_b;
+            envelope.gate_on();
+        }
+
+        let start = buffer.len();
+        buffer.resize(start + tone_frames, [0.0; CHANNELS]);
+        graph.audio_requested(&mut buffer[start..], sample_hz);
+
+        if let DspNode::Oscillator(_, _, _, ref mut envelope) = graph[oscillator_a] {
+            envelope.gate_off();
+        }
+        if let DspNode::Oscillator(_, _, _, ref mut envelope) = graph[oscillator_b] {
+            envelope.gate_off();
+        }
+
+        let start = buffer.len();
+        buffer.resize(start + gap_frames, [0.0; CHANNELS]);
+        graph.audio_requested(&mut buffer[start..], sample_hz);
+    }
+
+    buffer
+}
@@ -0,0 +1,168 @@
+//! Sample-accurate sequencing of DTMF tone/gap timing.
+//!
+//! Real DTMF receivers expect tones and the silence between them to last a
+//! fairly specific amount of time; timing this with `thread::sleep` on the
+//! sending thread is at the mercy of the scheduler. `Sequencer` instead
+//! measures tone-on and inter-digit-gap durations in samples, the same way
+//! the `synth` crate tracks a voice's `duration`, so a caller inside an
+//! audio callback can step it forward exactly one buffer at a time and
+//! always flip state on the right frame boundary.
+
+use std::collections::VecDeque;
+
+use crate::{to_frequencies, Frequency, Volume};
+
+/// What the caller should do in response to the sequencer entering a new
+/// state. Returned by [`Sequencer::advance`] alongside how many samples that
+/// state will last for (or until the next call, if it's still ongoing).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SequencerEvent {
+    /// A tone just started: gate the oscillators on with these frequencies.
+    StartTone(Frequency, Frequency),
+    /// The tone just ended: gate the oscillators off for the inter-digit gap.
+    StartGap,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Idle,
+    Tone(Frequency, Frequency, usize),
+    Gap(usize),
+}
+
+/// Queues up dialled characters and, sample by sample, decides when each
+/// tone should start and stop.
+pub struct Sequencer {
+    tone_samples: usize,
+    gap_samples: usize,
+    twist: Volume,
+    pending: VecDeque<(Frequency, Frequency)>,
+    state: State,
+}
+
+impl Sequencer {
+    /// A sequencer using the 70 ms on / 70 ms off timing and 2 dB twist that
+    /// real DTMF receivers are built to accept.
+    pub fn new(sample_hz: f64) -> Sequencer {
+        Sequencer::with_timing(
+            sample_hz,
+            crate::DEFAULT_TONE_MS,
+            crate::DEFAULT_GAP_MS,
+            crate::DEFAULT_TWIST_DB,
+        )
+    }
+
+    /// A sequencer with explicit tone/gap durations (in milliseconds) and
+    /// twist (in dB, applied to the high group relative to the low group).
+    pub fn with_timing(sample_hz: f64, tone_ms: f64, gap_ms: f64, twist_db: f64) -> Sequencer {
+        Sequencer {
+            tone_samples: ((tone_ms / 1_000.0 * sample_hz).round() as usize).max(1),
+            gap_samples: ((gap_ms / 1_000.0 * sample_hz).round() as usize).max(1),
+            twist: 10f64.powf(twist_db / 20.0) as Volume,
+            pending: VecDeque::new(),
+            state: State::Idle,
+        }
+    }
+
+    /// Queue a character to be dialled. Characters with no DTMF mapping are
+    /// silently dropped, same as the realtime `play` loop always did.
+    pub fn enqueue(&mut self, character: char) {
+        if let Some(frequencies) = to_frequencies(character) {
+            self.pending.push_back(frequencies);
+        }
+    }
+
+    /// Linear gain to apply to the high-group oscillator so it runs the
+    /// configured number of dB hotter than the low group.
+    pub fn twist(&self) -> Volume {
+        self.twist
+    }
+
+    /// Advance the sequencer by up to `max_samples` samples.
+    ///
+    /// Returns how many of those samples belong to the state the sequencer
+    /// is in right now, and, if this call just crossed into a new state, the
+    /// event the caller should react to. The caller is expected to render
+    /// exactly the returned number of samples before calling `advance`
+    /// again, so that state transitions always land on a frame boundary.
+    pub fn advance(&mut self, max_samples: usize) -> (usize, Option<SequencerEvent>) {
+        let max_samples = max_samples.max(1);
+
+        match self.state {
+            State::Idle => match self.pending.pop_front() {
+                Some((freq_a, freq_b)) => {
+                    let step = self.tone_samples.min(max_samples);
+                    self.state = State::Tone(freq_a, freq_b, self.tone_samples - step);
+                    (step, Some(SequencerEvent::StartTone(freq_a, freq_b)))
+                }
+                None => (max_samples, None),
+            },
+
+            State::Tone(freq_a, freq_b, remaining) => {
+                if remaining == 0 {
+                    let step = self.gap_samples.min(max_samples);
+                    self.state = State::Gap(self.gap_samples - step);
+                    (step, Some(SequencerEvent::StartGap))
+                } else {
+                    let step = remaining.min(max_samples);
+                    self.state = State::Tone(freq_a, freq_b, remaining - step);
+                    (step, None)
+                }
+            }
+
+            State::Gap(remaining) => {
+                if remaining == 0 {
+                    self.state = State::Idle;
+                    self.advance(max_samples)
+                } else {
+                    let step = remaining.min(max_samples);
+                    self.state = State::Gap(remaining - step);
+                    (step, None)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advances_exact_sample_counts_across_multiple_digits() {
+        let sample_hz = 8_000.0;
+        let tone_ms = 70.0;
+        let gap_ms = 70.0;
+        let digits = "1234";
+
+        let mut sequencer = Sequencer::with_timing(sample_hz, tone_ms, gap_ms, 0.0);
+        for character in digits.chars() {
+            sequencer.enqueue(character);
+        }
+
+        let tone_samples = (tone_ms / 1_000.0 * sample_hz).round() as usize;
+        let gap_samples = (gap_ms / 1_000.0 * sample_hz).round() as usize;
+        let expected_total = digits.chars().count() * (tone_samples + gap_samples);
+
+        let mut total = 0;
+        let mut tone_starts = 0;
+        let mut gap_starts = 0;
+
+        // Advance in chunks smaller than either a tone or a gap so that
+        // state transitions have to be picked up across separate calls,
+        // the same way the realtime callback advances one buffer at a time.
+        while total < expected_total {
+            let (step, event) = sequencer.advance(37);
+            match event {
+                Some(SequencerEvent::StartTone(_, _)) => tone_starts += 1,
+                Some(SequencerEvent::StartGap) => gap_starts += 1,
+                None => (),
+            }
+            total += step;
+        }
+
+        assert_eq!(total, expected_total);
+        assert_eq!(tone_starts, digits.chars().count());
+        assert_eq!(gap_starts, digits.chars().count());
+    }
+}
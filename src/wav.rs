@@ -0,0 +1,45 @@
+//! Minimal uncompressed WAV file writer for the buffers produced by
+//! [`crate::render::render_string`].
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::{Output, CHANNELS};
+
+/// Write `buffer` out as a 16-bit PCM WAV file at `sample_hz`.
+pub fn write_wav<P: AsRef<Path>>(path: P, buffer: &[[Output; CHANNELS]], sample_hz: f64) -> io::Result<()> {
+    let mut file = File::create(path)?;
+
+    let channels = CHANNELS as u16;
+    let bits_per_sample: u16 = 16;
+    let byte_rate = sample_hz as u32 * channels as u32 * bits_per_sample as u32 / 8;
+    let block_align = channels * bits_per_sample / 8;
+    let data_len = buffer.len() as u32 * block_align as u32;
+
+    file.write_all(b"RIFF")?;
+    file.write_all(&(36 + data_len).to_le_bytes())?;
+    file.write_all(b"WAVE")?;
+
+    file.write_all(b"fmt ")?;
+    file.write_all(&16u32.to_le_bytes())?;
+    file.write_all(&1u16.to_le_bytes())?; // PCM
+    file.write_all(&channels.to_le_bytes())?;
+    file.write_all(&(sample_hz as u32).to_le_bytes())?;
+    file.write_all(&byte_rate.to_le_bytes())?;
+    file.write_all(&block_align.to_le_bytes())?;
+    file.write_all(&bits_per_sample.to_le_bytes())?;
+
+    file.write_all(b"data")?;
+    file.write_all(&data_len.to_le_bytes())?;
+
+    for frame in buffer {
+        for &sample in frame {
+            let clamped = sample.max(-1.0).min(1.0);
+            let quantised = (clamped * i16::MAX as Output) as i16;
+            file.write_all(&quantised.to_le_bytes())?;
+        }
+    }
+
+    Ok(())
+}